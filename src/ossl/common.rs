@@ -0,0 +1,56 @@
+// Copyright 2024 Simo Sorce
+// See LICENSE.txt file for terms
+
+//! Shared OpenSSL EVP helpers used by the `ossl` mechanism modules.
+
+use std::os::raw::c_char;
+
+use crate::interface::*;
+
+/// Maps a PKCS#11 digest mechanism type to the OpenSSL algorithm name
+/// used to fetch the corresponding `EVP_MD`. Returns a null pointer for
+/// mechanism types this module does not implement as a digest.
+pub fn mech_type_to_digest_name(mech: CK_MECHANISM_TYPE) -> *const c_char {
+    match mech {
+        CKM_SHA_1 => c"SHA1".as_ptr(),
+        CKM_SHA224 => c"SHA224".as_ptr(),
+        CKM_SHA256 => c"SHA256".as_ptr(),
+        CKM_SHA384 => c"SHA384".as_ptr(),
+        CKM_SHA512 => c"SHA512".as_ptr(),
+        CKM_SHA3_224 => c"SHA3-224".as_ptr(),
+        CKM_SHA3_256 => c"SHA3-256".as_ptr(),
+        CKM_SHA3_384 => c"SHA3-384".as_ptr(),
+        CKM_SHA3_512 => c"SHA3-512".as_ptr(),
+        CKM_SHAKE_128 => c"SHAKE128".as_ptr(),
+        CKM_SHAKE_256 => c"SHAKE256".as_ptr(),
+        _ => std::ptr::null(),
+    }
+}
+
+/// Maps a PKCS#11 combined digest-and-sign mechanism type (e.g.
+/// `CKM_SHA256_RSA_PKCS`, `CKM_ECDSA_SHA256`) to the OpenSSL name of the
+/// digest it hashes with, for use with `EVP_DigestSignInit`/
+/// `EVP_DigestVerifyInit`. Returns a null pointer for mechanism types
+/// this module does not implement as a combined digest+sign mechanism.
+pub fn mech_type_to_sign_digest_name(
+    mech: CK_MECHANISM_TYPE,
+) -> *const c_char {
+    match mech {
+        CKM_SHA1_RSA_PKCS | CKM_SHA1_RSA_PKCS_PSS | CKM_ECDSA_SHA1 => {
+            c"SHA1".as_ptr()
+        }
+        CKM_SHA224_RSA_PKCS | CKM_SHA224_RSA_PKCS_PSS | CKM_ECDSA_SHA224 => {
+            c"SHA224".as_ptr()
+        }
+        CKM_SHA256_RSA_PKCS | CKM_SHA256_RSA_PKCS_PSS | CKM_ECDSA_SHA256 => {
+            c"SHA256".as_ptr()
+        }
+        CKM_SHA384_RSA_PKCS | CKM_SHA384_RSA_PKCS_PSS | CKM_ECDSA_SHA384 => {
+            c"SHA384".as_ptr()
+        }
+        CKM_SHA512_RSA_PKCS | CKM_SHA512_RSA_PKCS_PSS | CKM_ECDSA_SHA512 => {
+            c"SHA512".as_ptr()
+        }
+        _ => std::ptr::null(),
+    }
+}
@@ -43,6 +43,22 @@ impl HashState {
             ctx: EvpMdCtx::new()?,
         })
     }
+
+    /// Builds a `HashState` by duplicating the state of `src`, fetching a
+    /// fresh `EVP_MD` for `alg` and copying `src`'s `EVP_MD_CTX` into a
+    /// new context via `EVP_MD_CTX_copy_ex`, rather than initializing an
+    /// empty one via `EVP_MD_CTX_new`.
+    pub fn copy(alg: *const c_char, src: &HashState) -> Result<HashState> {
+        let md = EvpMd::new(alg)?;
+        let mut ctx = EvpMdCtx::new()?;
+        let r = unsafe {
+            EVP_MD_CTX_copy_ex(ctx.as_mut_ptr(), src.ctx.as_ptr())
+        };
+        if r != 1 {
+            return Err(CKR_DEVICE_ERROR)?;
+        }
+        Ok(HashState { md: md, ctx: ctx })
+    }
 }
 
 unsafe impl Send for HashState {}
@@ -76,6 +92,32 @@ impl HashOperation {
             }
         }
     }
+
+    /// True if this mechanism is an extendable-output function (XOF).
+    fn is_xof(&self) -> bool {
+        matches!(self.mech, CKM_SHAKE_128 | CKM_SHAKE_256)
+    }
+
+    /// Duplicates a partially-updated hash so callers can snapshot a
+    /// common prefix (e.g. hash a shared header once, then branch to
+    /// finalize several variants) without re-feeding the shared data.
+    /// Only an operation that has been updated at least once and not yet
+    /// finalized can be cloned.
+    pub fn try_clone(&self) -> Result<HashOperation> {
+        if !self.in_use || self.finalized {
+            return Err(CKR_OPERATION_NOT_INITIALIZED)?;
+        }
+        let alg: *const c_char = mech_type_to_digest_name(self.mech);
+        if alg.is_null() {
+            return Err(CKR_MECHANISM_INVALID)?;
+        }
+        Ok(HashOperation {
+            mech: self.mech,
+            state: HashState::copy(alg, &self.state)?,
+            finalized: self.finalized,
+            in_use: self.in_use,
+        })
+    }
 }
 
 impl MechOperation for HashOperation {
@@ -87,6 +129,11 @@ impl MechOperation for HashOperation {
         self.finalized
     }
     fn reset(&mut self) -> Result<()> {
+        let r = unsafe { EVP_MD_CTX_reset(self.state.ctx.as_mut_ptr()) };
+        if r != 1 {
+            return Err(CKR_DEVICE_ERROR)?;
+        }
+        self.digest_init()?;
         self.finalized = false;
         self.in_use = false;
         Ok(())
@@ -98,10 +145,36 @@ impl Digest for HashOperation {
         if self.in_use || self.finalized {
             return Err(CKR_OPERATION_NOT_INITIALIZED)?;
         }
-        if digest.len() != self.digest_len()? {
+        if !self.is_xof() && digest.len() != self.digest_len()? {
             return Err(CKR_GENERAL_ERROR)?;
         }
         self.finalized = true;
+        if self.is_xof() {
+            /* EVP_Digest() does not support XOF output, so drive the
+             * streaming API directly for the one-shot case. */
+            self.digest_init()?;
+            let r = unsafe {
+                EVP_DigestUpdate(
+                    self.state.ctx.as_mut_ptr(),
+                    data.as_ptr() as *const c_void,
+                    data.len(),
+                )
+            };
+            if r != 1 {
+                return Err(CKR_DEVICE_ERROR)?;
+            }
+            let r = unsafe {
+                EVP_DigestFinalXOF(
+                    self.state.ctx.as_mut_ptr(),
+                    digest.as_mut_ptr(),
+                    digest.len(),
+                )
+            };
+            return match r {
+                1 => Ok(()),
+                _ => Err(CKR_GENERAL_ERROR)?,
+            };
+        }
         /* NOTE: It is ok if data and digest point to the same buffer*/
         let mut digest_len = c_uint::try_from(self.digest_len()?)?;
         let r = unsafe {
@@ -151,10 +224,23 @@ impl Digest for HashOperation {
         if self.finalized {
             return Err(CKR_OPERATION_NOT_INITIALIZED)?;
         }
-        if digest.len() != self.digest_len()? {
+        if !self.is_xof() && digest.len() != self.digest_len()? {
             return Err(CKR_GENERAL_ERROR)?;
         }
         self.finalized = true;
+        if self.is_xof() {
+            let r = unsafe {
+                EVP_DigestFinalXOF(
+                    self.state.ctx.as_mut_ptr(),
+                    digest.as_mut_ptr(),
+                    digest.len(),
+                )
+            };
+            return match r {
+                1 => Ok(()),
+                _ => Err(CKR_GENERAL_ERROR)?,
+            };
+        }
         let mut digest_len = c_uint::try_from(self.digest_len()?)?;
         let r = unsafe {
             EVP_DigestFinal_ex(
@@ -169,8 +255,351 @@ impl Digest for HashOperation {
         Ok(())
     }
 
+    /// Returns the digest output size in bytes; not meaningful for XOFs.
     fn digest_len(&self) -> Result<usize> {
         let len = unsafe { EVP_MD_get_size(self.state.md.as_ptr()) };
         Ok(usize::try_from(len)?)
     }
 }
+
+/// Represents an active combined digest-and-sign operation, driving
+/// `EVP_DigestSign*` over the same `EVP_MD_CTX` used for plain digests.
+#[derive(Debug)]
+pub struct DigestSignOperation {
+    /// The specific digest+sign mechanism being used (e.g.
+    /// CKM_ECDSA_SHA256).
+    mech: CK_MECHANISM_TYPE,
+    /// The underlying OpenSSL state (algorithm and context).
+    state: HashState,
+    /// The signing key, kept alive for the lifetime of the context and
+    /// reused by `reset()` to re-arm the operation.
+    key: EvpPkey,
+    /// Flag indicating if the operation has been finalized.
+    finalized: bool,
+    /// Flag indicating if the operation is in progress (update called).
+    in_use: bool,
+}
+
+impl DigestSignOperation {
+    /// Creates a new `DigestSignOperation` for `mech`, initializing the
+    /// context to sign with `key` via `EVP_DigestSignInit`.
+    pub fn new(
+        mech: CK_MECHANISM_TYPE,
+        key: EvpPkey,
+    ) -> Result<DigestSignOperation> {
+        let alg: *const c_char = mech_type_to_sign_digest_name(mech);
+        if alg.is_null() {
+            return Err(CKR_MECHANISM_INVALID)?;
+        }
+        let mut state = HashState::new(alg)?;
+        let r = unsafe {
+            EVP_DigestSignInit(
+                state.ctx.as_mut_ptr(),
+                std::ptr::null_mut(),
+                state.md.as_ptr(),
+                std::ptr::null_mut(),
+                key.as_ptr(),
+            )
+        };
+        if r != 1 {
+            return Err(CKR_DEVICE_ERROR)?;
+        }
+        Ok(DigestSignOperation {
+            mech: mech,
+            state: state,
+            key: key,
+            finalized: false,
+            in_use: false,
+        })
+    }
+
+    /// Feeds more data into the digest-and-sign context
+    /// (`EVP_DigestSignUpdate`).
+    pub fn sign_update(&mut self, data: &[u8]) -> Result<()> {
+        if self.finalized {
+            return Err(CKR_OPERATION_NOT_INITIALIZED)?;
+        }
+        self.in_use = true;
+        let r = unsafe {
+            EVP_DigestSignUpdate(
+                self.state.ctx.as_mut_ptr(),
+                data.as_ptr() as *const c_void,
+                data.len(),
+            )
+        };
+        match r {
+            1 => Ok(()),
+            _ => {
+                self.finalized = true;
+                Err(CKR_DEVICE_ERROR)?
+            }
+        }
+    }
+
+    /// Finalizes the operation and produces the signature
+    /// (`EVP_DigestSignFinal`). As with `C_SignFinal`, callers are
+    /// expected to call this first with an empty `signature` slice to
+    /// learn the required length, then again with a buffer of that
+    /// length to retrieve the signature.
+    pub fn sign_final(&mut self, signature: &mut [u8]) -> Result<usize> {
+        if !self.in_use || self.finalized {
+            return Err(CKR_OPERATION_NOT_INITIALIZED)?;
+        }
+        let mut siglen: usize = signature.len();
+        let sigptr = if signature.is_empty() {
+            std::ptr::null_mut()
+        } else {
+            signature.as_mut_ptr()
+        };
+        let r = unsafe {
+            EVP_DigestSignFinal(self.state.ctx.as_mut_ptr(), sigptr, &mut siglen)
+        };
+        if r != 1 {
+            self.finalized = true;
+            return Err(CKR_DEVICE_ERROR)?;
+        }
+        if !signature.is_empty() {
+            self.finalized = true;
+        }
+        Ok(siglen)
+    }
+}
+
+impl MechOperation for DigestSignOperation {
+    fn mechanism(&self) -> Result<CK_MECHANISM_TYPE> {
+        Ok(self.mech)
+    }
+    fn finalized(&self) -> bool {
+        self.finalized
+    }
+    fn reset(&mut self) -> Result<()> {
+        let r = unsafe { EVP_MD_CTX_reset(self.state.ctx.as_mut_ptr()) };
+        if r != 1 {
+            return Err(CKR_DEVICE_ERROR)?;
+        }
+        let r = unsafe {
+            EVP_DigestSignInit(
+                self.state.ctx.as_mut_ptr(),
+                std::ptr::null_mut(),
+                self.state.md.as_ptr(),
+                std::ptr::null_mut(),
+                self.key.as_ptr(),
+            )
+        };
+        if r != 1 {
+            return Err(CKR_DEVICE_ERROR)?;
+        }
+        self.finalized = false;
+        self.in_use = false;
+        Ok(())
+    }
+}
+
+/// Represents an active combined digest-and-verify operation, the
+/// `EVP_DigestVerify*` counterpart to `DigestSignOperation`.
+#[derive(Debug)]
+pub struct DigestVerifyOperation {
+    /// The specific digest+verify mechanism being used (e.g.
+    /// CKM_ECDSA_SHA256).
+    mech: CK_MECHANISM_TYPE,
+    /// The underlying OpenSSL state (algorithm and context).
+    state: HashState,
+    /// The verification key, kept alive for the lifetime of the context
+    /// and reused by `reset()` to re-arm the operation.
+    key: EvpPkey,
+    /// Flag indicating if the operation has been finalized.
+    finalized: bool,
+    /// Flag indicating if the operation is in progress (update called).
+    in_use: bool,
+}
+
+impl DigestVerifyOperation {
+    /// Creates a new `DigestVerifyOperation` for `mech`, initializing the
+    /// context to verify against `key` via `EVP_DigestVerifyInit`.
+    pub fn new(
+        mech: CK_MECHANISM_TYPE,
+        key: EvpPkey,
+    ) -> Result<DigestVerifyOperation> {
+        let alg: *const c_char = mech_type_to_sign_digest_name(mech);
+        if alg.is_null() {
+            return Err(CKR_MECHANISM_INVALID)?;
+        }
+        let mut state = HashState::new(alg)?;
+        let r = unsafe {
+            EVP_DigestVerifyInit(
+                state.ctx.as_mut_ptr(),
+                std::ptr::null_mut(),
+                state.md.as_ptr(),
+                std::ptr::null_mut(),
+                key.as_ptr(),
+            )
+        };
+        if r != 1 {
+            return Err(CKR_DEVICE_ERROR)?;
+        }
+        Ok(DigestVerifyOperation {
+            mech: mech,
+            state: state,
+            key: key,
+            finalized: false,
+            in_use: false,
+        })
+    }
+
+    /// Feeds more data into the digest-and-verify context
+    /// (`EVP_DigestVerifyUpdate`).
+    pub fn verify_update(&mut self, data: &[u8]) -> Result<()> {
+        if self.finalized {
+            return Err(CKR_OPERATION_NOT_INITIALIZED)?;
+        }
+        self.in_use = true;
+        let r = unsafe {
+            EVP_DigestVerifyUpdate(
+                self.state.ctx.as_mut_ptr(),
+                data.as_ptr() as *const c_void,
+                data.len(),
+            )
+        };
+        match r {
+            1 => Ok(()),
+            _ => {
+                self.finalized = true;
+                Err(CKR_DEVICE_ERROR)?
+            }
+        }
+    }
+
+    /// Finalizes the operation and checks `signature`
+    /// (`EVP_DigestVerifyFinal`).
+    pub fn verify_final(&mut self, signature: &[u8]) -> Result<()> {
+        if !self.in_use || self.finalized {
+            return Err(CKR_OPERATION_NOT_INITIALIZED)?;
+        }
+        self.finalized = true;
+        let r = unsafe {
+            EVP_DigestVerifyFinal(
+                self.state.ctx.as_mut_ptr(),
+                signature.as_ptr(),
+                signature.len(),
+            )
+        };
+        match r {
+            1 => Ok(()),
+            0 => Err(CKR_SIGNATURE_INVALID)?,
+            _ => Err(CKR_DEVICE_ERROR)?,
+        }
+    }
+}
+
+impl MechOperation for DigestVerifyOperation {
+    fn mechanism(&self) -> Result<CK_MECHANISM_TYPE> {
+        Ok(self.mech)
+    }
+    fn finalized(&self) -> bool {
+        self.finalized
+    }
+    fn reset(&mut self) -> Result<()> {
+        let r = unsafe { EVP_MD_CTX_reset(self.state.ctx.as_mut_ptr()) };
+        if r != 1 {
+            return Err(CKR_DEVICE_ERROR)?;
+        }
+        let r = unsafe {
+            EVP_DigestVerifyInit(
+                self.state.ctx.as_mut_ptr(),
+                std::ptr::null_mut(),
+                self.state.md.as_ptr(),
+                std::ptr::null_mut(),
+                self.key.as_ptr(),
+            )
+        };
+        if r != 1 {
+            return Err(CKR_DEVICE_ERROR)?;
+        }
+        self.finalized = false;
+        self.in_use = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_sha3_256_and_shake128() {
+        HashOperation::new(CKM_SHA3_256).unwrap();
+        HashOperation::new(CKM_SHAKE_128).unwrap();
+    }
+
+    #[test]
+    fn shake128_one_shot_arbitrary_length() {
+        let mut op = HashOperation::new(CKM_SHAKE_128).unwrap();
+        let mut out = [0u8; 17];
+        op.digest(b"hello", &mut out).unwrap();
+        assert_ne!(out, [0u8; 17]);
+    }
+
+    #[test]
+    fn shake256_multi_part_arbitrary_length() {
+        let mut op = HashOperation::new(CKM_SHAKE_256).unwrap();
+        op.digest_update(b"hel").unwrap();
+        op.digest_update(b"lo").unwrap();
+        let mut out = [0u8; 33];
+        op.digest_final(&mut out).unwrap();
+        assert_ne!(out, [0u8; 33]);
+    }
+
+    #[test]
+    fn try_clone_matches_continuation() {
+        let mut op = HashOperation::new(CKM_SHA256).unwrap();
+        op.digest_update(b"shared-prefix-").unwrap();
+        let mut clone = op.try_clone().unwrap();
+
+        op.digest_update(b"tail").unwrap();
+        clone.digest_update(b"tail").unwrap();
+
+        let mut out = [0u8; 32];
+        let mut clone_out = [0u8; 32];
+        op.digest_final(&mut out).unwrap();
+        clone.digest_final(&mut clone_out).unwrap();
+        assert_eq!(out, clone_out);
+    }
+
+    #[test]
+    fn reset_then_reuse_digests_new_input() {
+        let mut op = HashOperation::new(CKM_SHA256).unwrap();
+        op.digest_update(b"first-input").unwrap();
+        let mut first = [0u8; 32];
+        op.digest_final(&mut first).unwrap();
+
+        op.reset().unwrap();
+        op.digest_update(b"second-input").unwrap();
+        let mut second = [0u8; 32];
+        op.digest_final(&mut second).unwrap();
+
+        let mut direct = HashOperation::new(CKM_SHA256).unwrap();
+        let mut expected = [0u8; 32];
+        direct.digest(b"second-input", &mut expected).unwrap();
+        assert_eq!(second, expected);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn sign_then_verify_round_trip() {
+        let key = EvpPkey::generate_rsa(2048).unwrap();
+
+        let mut signer =
+            DigestSignOperation::new(CKM_SHA256_RSA_PKCS, key.try_clone().unwrap())
+                .unwrap();
+        signer.sign_update(b"hello world").unwrap();
+        let siglen = signer.sign_final(&mut []).unwrap();
+        let mut sig = vec![0u8; siglen];
+        let siglen = signer.sign_final(&mut sig).unwrap();
+        sig.truncate(siglen);
+
+        let mut verifier =
+            DigestVerifyOperation::new(CKM_SHA256_RSA_PKCS, key).unwrap();
+        verifier.verify_update(b"hello world").unwrap();
+        verifier.verify_final(&sig).unwrap();
+    }
+}